@@ -1,4 +1,11 @@
-//! TODO
+//! Supporting functions and types for converting a [`Grid`]'s channels between particle bases.
+
+use super::empty_subgrid::EmptySubgridV1;
+use super::grid::Grid;
+use super::lumi_entry;
+use super::subgrid::{Subgrid, SubgridEnum};
+use ndarray::Array3;
+use std::mem;
 
 /// Translates IDs from the evolution basis into IDs using PDG Monte Carlo IDs.
 #[must_use]
@@ -148,10 +155,156 @@ pub fn charge_conjugate(lumi_id_types: &str, pid: i32) -> (i32, f64) {
     }
 }
 
+/// The basis in which a [`Grid`]'s channels (luminosity functions) are expressed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LumiIdTypes {
+    /// The evolution basis: singlet, gluon, T3, T8, ..., V, V3, ....
+    Evol,
+    /// PDG Monte Carlo particle IDs.
+    PdgMcIds,
+}
+
+impl LumiIdTypes {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Evol => "evol",
+            Self::PdgMcIds => "pdg_mc_ids",
+        }
+    }
+}
+
+impl Grid {
+    /// Rewrites every channel and subgrid of this `Grid` from the basis given by the
+    /// `lumi_id_types` key-value pair into `target`, using the linear combinations from
+    /// [`evol_to_pdg_mc_ids`] to expand each evolution-basis PID into PDG Monte Carlo IDs.
+    /// Channels that expand onto the same flavor pair are summed, and `lumi_id_types` is updated
+    /// to reflect the new basis.
+    ///
+    /// Currently only the conversion from [`LumiIdTypes::Evol`] to [`LumiIdTypes::PdgMcIds`] is
+    /// supported, which is the direction needed to make FK tables produced by
+    /// `convert_fktable` (which hard-codes `evol`) consumable by tools that only understand
+    /// flavor-basis PDFs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid's current basis isn't `evol`, or if `target` isn't
+    /// [`LumiIdTypes::PdgMcIds`].
+    pub fn rotate_lumi_basis(&mut self, target: LumiIdTypes) {
+        let current = self
+            .key_values()
+            .and_then(|kv| kv.get("lumi_id_types").cloned())
+            .unwrap_or_else(|| LumiIdTypes::PdgMcIds.as_str().to_string());
+
+        assert_eq!(
+            (current.as_str(), target),
+            (LumiIdTypes::Evol.as_str(), LumiIdTypes::PdgMcIds),
+            "rotating from '{current}' to '{}' is not supported",
+            target.as_str(),
+        );
+
+        // expand every channel into its flavor-pair decomposition, remembering which new flavor
+        // pairs (and with which relative weight) each old channel contributes to; a single old
+        // channel is typically a source for many new ones (e.g. the singlet expands into 12 PDG
+        // pairs), so we key this by `old_lumi` to make sure each source subgrid is only taken out
+        // of `subgrids` once
+        let mut new_lumis: Vec<(i32, i32)> = Vec::new();
+        let mut targets: Vec<Vec<(usize, f64)>> = vec![Vec::new(); self.lumi().len()];
+
+        for (lumi_index, entry) in self.lumi().iter().enumerate() {
+            for &(pida, pidb, factor) in entry.entry() {
+                for &(a, fa) in &evol_to_pdg_mc_ids(pida) {
+                    for &(b, fb) in &evol_to_pdg_mc_ids(pidb) {
+                        let weight = factor * fa * fb;
+
+                        let new_lumi = match new_lumis.iter().position(|&pair| pair == (a, b)) {
+                            Some(index) => index,
+                            None => {
+                                new_lumis.push((a, b));
+                                new_lumis.len() - 1
+                            }
+                        };
+
+                        targets[lumi_index].push((new_lumi, weight));
+                    }
+                }
+            }
+        }
+
+        let orders = self.orders().len();
+        let bins = self.bin_info().bins();
+        let mut subgrids = self.subgrids_mut();
+        let mut new_subgrids: Array3<SubgridEnum> =
+            Array3::from_shape_simple_fn((orders, bins, new_lumis.len()), || {
+                EmptySubgridV1::default().into()
+            });
+
+        for order in 0..orders {
+            for bin in 0..bins {
+                for (old_lumi, new_lumi_weights) in targets.iter().enumerate() {
+                    // take the source subgrid out exactly once, then merge a scaled clone of it
+                    // into every new channel it contributes to
+                    let subgrid = mem::replace(
+                        &mut subgrids[[order, bin, old_lumi]],
+                        EmptySubgridV1::default().into(),
+                    );
+
+                    for &(new_lumi, weight) in new_lumi_weights {
+                        let mut scaled = subgrid.clone();
+                        scaled.scale(weight);
+                        new_subgrids[[order, bin, new_lumi]].merge(&scaled, false);
+                    }
+                }
+            }
+        }
+
+        self.set_subgrids(new_subgrids);
+        self.set_lumis(
+            new_lumis
+                .into_iter()
+                .map(|(a, b)| lumi_entry![a, b, 1.0])
+                .collect(),
+        );
+        self.key_values_mut().insert(
+            "lumi_id_types".to_string(),
+            target.as_str().to_string(),
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::super::grid::Order;
+    use super::super::subgrid::SubgridParams;
     use super::*;
 
+    // regression test for a channel whose evolution-basis PID fans out into more than one PDG
+    // flavor pair: every pair must show up exactly once in the rotated basis, which only holds if
+    // every fan-out target is actually visited (rather than only the first one to consume the
+    // source subgrid)
+    #[test]
+    fn rotate_lumi_basis_keeps_every_fan_out_target() {
+        let mut grid = Grid::new(
+            vec![lumi_entry![100, 11, 1.0]],
+            vec![Order::new(0, 0, 0, 0)],
+            vec![0.0, 1.0],
+            SubgridParams::default(),
+        );
+        grid.key_values_mut()
+            .insert("lumi_id_types".to_string(), "evol".to_string());
+
+        grid.rotate_lumi_basis(LumiIdTypes::PdgMcIds);
+
+        let expanded = evol_to_pdg_mc_ids(100);
+        assert_eq!(grid.lumi().len(), expanded.len());
+
+        for &(pid, _) in &expanded {
+            assert!(grid
+                .lumi()
+                .iter()
+                .any(|entry| entry.entry() == [(pid, 11, 1.0)]));
+        }
+    }
+
     #[test]
     fn test() {
         // check photon
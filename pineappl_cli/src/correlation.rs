@@ -0,0 +1,140 @@
+use super::helpers::{self, GlobalConfiguration, Subcommand};
+use anyhow::Result;
+use clap::{Parser, ValueHint};
+use prettytable::{cell, row};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Computes the per-bin PDF-induced correlation matrix of a grid.
+#[derive(Parser)]
+pub struct Opts {
+    /// Path to the input grid.
+    #[arg(value_hint = ValueHint::FilePath)]
+    input: PathBuf,
+    /// LHAPDF id or name of the PDF set.
+    #[arg(value_parser = helpers::validate_pdfset)]
+    pdfset: String,
+}
+
+// Hessian correlation: rho(A,B) = sum_k (A+ - A-)(B+ - B-) / (4 * delta_A * delta_B), with
+// delta_X = 1/2 * sqrt(sum_k (X+ - X-)^2) over the eigenvector pairs (2k-1, 2k).
+fn hessian_correlation(values: &[Vec<f64>], a: usize, b: usize) -> f64 {
+    let delta = |bin: usize| {
+        0.5 * values[1..]
+            .chunks_exact(2)
+            .map(|pair| (pair[0][bin] - pair[1][bin]).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+
+    let cov: f64 = values[1..]
+        .chunks_exact(2)
+        .map(|pair| (pair[0][a] - pair[1][a]) * (pair[0][b] - pair[1][b]))
+        .sum();
+
+    cov / (4.0 * delta(a) * delta(b))
+}
+
+// Symmetric-Hessian correlation: rho(A,B) = sum_i (A_i-A_0)(B_i-B_0) / (delta_A * delta_B), with
+// delta_X = sqrt(sum_i (X_i-X_0)^2) over the (unpaired) eigenvector members; unlike `hessian` the
+// members aren't +/- pairs of the same eigenvector, so there's no factor of 4 and no pairing.
+fn symmhessian_correlation(values: &[Vec<f64>], a: usize, b: usize) -> f64 {
+    let delta = |bin: usize| {
+        values[1..]
+            .iter()
+            .map(|member| (member[bin] - values[0][bin]).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+
+    let cov: f64 = values[1..]
+        .iter()
+        .map(|member| (member[a] - values[0][a]) * (member[b] - values[0][b]))
+        .sum();
+
+    cov / (delta(a) * delta(b))
+}
+
+// Replica correlation: rho(A,B) = (<AB> - <A><B>) / (sigma_A * sigma_B), with sample means and
+// standard deviations over the replica members.
+fn replica_correlation(values: &[Vec<f64>], a: usize, b: usize) -> f64 {
+    let n = values.len() as f64;
+    let mean = |bin: usize| values.iter().map(|member| member[bin]).sum::<f64>() / n;
+    let std_dev = |bin: usize, mean: f64| {
+        (values
+            .iter()
+            .map(|member| (member[bin] - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0))
+            .sqrt()
+    };
+
+    let mean_a = mean(a);
+    let mean_b = mean(b);
+    let cov = values
+        .iter()
+        .map(|member| (member[a] - mean_a) * (member[b] - mean_b))
+        .sum::<f64>()
+        / (n - 1.0);
+
+    cov / (std_dev(a, mean_a) * std_dev(b, mean_b))
+}
+
+impl Subcommand for Opts {
+    fn run(&self, _: &GlobalConfiguration) -> Result<ExitCode> {
+        let grid = helpers::read_grid(&self.input)?;
+        let (set, _) = helpers::create_pdfset(&self.pdfset)?;
+        let error_type = set
+            .entry("ErrorType")
+            .unwrap_or_else(|| "symmhessian".to_string());
+
+        let values: Vec<Vec<f64>> = set
+            .mk_pdfs()
+            .into_iter()
+            .map(|mut pdf| {
+                helpers::convolute(
+                    &grid,
+                    &mut pdf,
+                    None,
+                    &[],
+                    &[],
+                    &[],
+                    1,
+                    helpers::ConvoluteMode::Normal,
+                    false,
+                )
+            })
+            .collect();
+
+        let n_bins = grid.bin_info().bins();
+
+        let correlation: fn(&[Vec<f64>], usize, usize) -> f64 = match error_type.as_str() {
+            "replicas" => replica_correlation,
+            "hessian" => hessian_correlation,
+            // symmhessian and anything else we don't recognize
+            _ => symmhessian_correlation,
+        };
+
+        let mut title = row![];
+        title.add_cell(cell!(c->"bin"));
+        for bin in 0..n_bins {
+            title.add_cell(cell!(c->&format!("{bin}")));
+        }
+
+        let mut table = helpers::create_table();
+        table.set_titles(title);
+
+        for a in 0..n_bins {
+            let row = table.add_empty_row();
+            row.add_cell(cell!(r->&format!("{a}")));
+
+            for b in 0..n_bins {
+                row.add_cell(cell!(r->&format!("{:.3}", correlation(&values, a, b))));
+            }
+        }
+
+        table.printstd();
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
@@ -20,6 +20,15 @@ pub fn create_pdf(pdf: &str) -> Result<Pdf> {
         .map_or_else(|_| Pdf::with_setname_and_nmem(pdf), Pdf::with_lhaid)?)
 }
 
+/// Parses the `pdfa,pdfb` CLI syntax used for asymmetric-beam convolutions, falling back to a
+/// single PDF for both beams when only one is given.
+pub fn create_pdfs(pdf: &str) -> Result<(Pdf, Option<Pdf>)> {
+    match pdf.split_once(',') {
+        Some((pdfa, pdfb)) => Ok((create_pdf(pdfa)?, Some(create_pdf(pdfb)?))),
+        None => Ok((create_pdf(pdf)?, None)),
+    }
+}
+
 pub fn create_pdfset(pdfset: &str) -> Result<(PdfSet, Option<usize>)> {
     let pdfset = pdfset.rsplit_once('=').map_or(pdfset, |(name, _)| name);
     let (pdfset, member) = pdfset
@@ -129,6 +138,7 @@ pub enum ConvoluteMode {
 pub fn convolute(
     grid: &Grid,
     lhapdf: &mut Pdf,
+    lhapdf2: Option<&mut Pdf>,
     orders: &[(u32, u32)],
     bins: &[usize],
     lumis: &[bool],
@@ -168,8 +178,38 @@ pub fn convolute(
         }
     };
     let mut alphas = |q2| lhapdf.alphas_q2(q2);
-    let mut cache = LumiCache::with_one(pdf_pdg_id, &mut pdf, &mut alphas);
-    let mut results = grid.convolute(&mut cache, &orders, bins, lumis, &SCALES_VECTOR[0..scales]);
+
+    let mut results = if let Some(lhapdf2) = lhapdf2 {
+        // proton-nucleus/unequal-beam convolution: x1 is evaluated against `lhapdf`, x2 against
+        // `lhapdf2`
+        let pdf_pdg_id2 = lhapdf2
+            .set()
+            .entry("Particle")
+            .map_or(Ok(2212), |string| string.parse::<i32>())
+            .unwrap();
+
+        if force_positive {
+            lhapdf2.set_force_positive(1);
+        }
+
+        let x_max2 = lhapdf2.x_max();
+        let x_min2 = lhapdf2.x_min();
+        let mut pdf2 = |id, x, q2| {
+            if x < x_min2 || x > x_max2 {
+                0.0
+            } else {
+                lhapdf2.xfx_q2(id, x, q2)
+            }
+        };
+        let mut cache =
+            LumiCache::with_two(pdf_pdg_id, &mut pdf, pdf_pdg_id2, &mut pdf2, &mut alphas);
+
+        grid.convolute(&mut cache, &orders, bins, lumis, &SCALES_VECTOR[0..scales])
+    } else {
+        let mut cache = LumiCache::with_one(pdf_pdg_id, &mut pdf, &mut alphas);
+
+        grid.convolute(&mut cache, &orders, bins, lumis, &SCALES_VECTOR[0..scales])
+    };
 
     match mode {
         ConvoluteMode::Integrated => {
@@ -192,6 +232,366 @@ pub fn convolute(
     results
 }
 
+/// Per-bin central value and asymmetric uncertainty, as returned by [`convolute_uncertainty`].
+pub struct PdfUncertainty {
+    pub central: Vec<f64>,
+    pub errminus: Vec<f64>,
+    pub errplus: Vec<f64>,
+}
+
+/// Inverse of the standard normal CDF, using Acklam's rational approximation. Used to rescale an
+/// uncertainty band from the PDF set's native confidence level to the one requested by the
+/// caller, assuming the underlying distribution is Gaussian.
+fn norm_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e+01,
+        2.209_460_984_245_205e+02,
+        -2.759_285_104_469_687e+02,
+        1.383_577_518_672_690e+02,
+        -3.066_479_806_614_716e+01,
+        2.506_628_277_459_239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e+01,
+        1.615_858_368_580_409e+02,
+        -1.556_989_798_598_866e+02,
+        6.680_131_188_771_972e+01,
+        -1.328_068_155_288_572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-03,
+        -3.223_964_580_411_365e-01,
+        -2.400_758_277_161_838e+00,
+        -2.549_732_539_343_734e+00,
+        4.374_664_141_464_968e+00,
+        2.938_163_982_698_783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-03,
+        3.224_671_290_700_398e-01,
+        2.445_134_137_142_996e+00,
+        3.754_408_661_907_416e+00,
+    ];
+
+    let p_low = 0.024_25;
+    let p_high = 1.0 - p_low;
+
+    if p < p_low {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= p_high {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        let q = (-2.0 * (1.0 - p).ln()).sqrt();
+        -(((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    }
+}
+
+fn gaussian_quantile(cl_percent: f64) -> f64 {
+    norm_quantile(0.5 + cl_percent / 200.0)
+}
+
+/// How a Monte-Carlo replica set's uncertainty band should be summarized by
+/// [`combine_members`].
+#[derive(Clone, Copy)]
+pub enum ReplicaBand {
+    /// Report the sample standard deviation of the replicas.
+    StdDev,
+    /// Report the symmetric `cl`%-percentile band of the replicas.
+    Percentile,
+}
+
+/// Combines `values` — one convoluted result per PDF-set member, all for the same bin — into a
+/// central value and asymmetric uncertainty, using the combination formula implied by
+/// `error_type` (`hessian`, `replicas`, or symmhessian/anything else unrecognized). For
+/// `"replicas"`, `band` selects between the sample standard deviation and the symmetric `cl`%
+/// percentile band; `cl` is unused otherwise.
+pub fn combine_members(
+    values: &[f64],
+    error_type: &str,
+    cl: f64,
+    band: ReplicaBand,
+) -> (f64, f64, f64) {
+    match error_type {
+        "hessian" => {
+            let central = values[0];
+            let errplus = values[1..]
+                .chunks_exact(2)
+                .map(|pair| (pair[0] - central).max(pair[1] - central).max(0.0).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            let errminus = values[1..]
+                .chunks_exact(2)
+                .map(|pair| (central - pair[0]).max(central - pair[1]).max(0.0).powi(2))
+                .sum::<f64>()
+                .sqrt();
+
+            (central, -errminus, errplus)
+        }
+        "replicas" => {
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+
+            match band {
+                ReplicaBand::StdDev => {
+                    let std_dev =
+                        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0))
+                            .sqrt();
+
+                    (mean, -std_dev, std_dev)
+                }
+                ReplicaBand::Percentile => {
+                    let mut sorted = values.to_vec();
+                    sorted.sort_by(f64::total_cmp);
+
+                    let tail = (1.0 - cl / 100.0) / 2.0;
+                    let lo = sorted[(tail * (sorted.len() - 1) as f64).round() as usize];
+                    let hi = sorted[((1.0 - tail) * (sorted.len() - 1) as f64).round() as usize];
+
+                    (mean, lo - mean, hi - mean)
+                }
+            }
+        }
+        // symmhessian and anything else we don't recognize
+        _ => {
+            let central = values[0];
+            let errsymm = values[1..]
+                .iter()
+                .map(|value| (value - central).powi(2))
+                .sum::<f64>()
+                .sqrt();
+
+            (central, -errsymm, errsymm)
+        }
+    }
+}
+
+/// Convolutes `grid` once per member of `set` (reusing one `LumiCache` per member) and combines
+/// the per-bin results into a central value and an uncertainty band, using the combination
+/// formula implied by the set's `ErrorType` metadata (`symmhessian`, `hessian`, or `replicas`).
+/// `cl` is the requested confidence level in percent; the result is rescaled from the set's own
+/// (`ErrorConfLevel`) confidence level assuming both are Gaussian.
+pub fn convolute_uncertainty(
+    grid: &Grid,
+    set: &PdfSet,
+    orders: &[(u32, u32)],
+    bins: &[usize],
+    lumis: &[bool],
+    scales: usize,
+    cl: f64,
+) -> Result<PdfUncertainty> {
+    // `results` is bin-major/scale-minor (see `convolute`'s `SCALES_VECTOR[0..scales]`), but a PDF
+    // uncertainty band is only meaningful for a single, central scale
+    ensure!(
+        scales == 1,
+        "PDF uncertainty can only be computed for a single scale, found {scales}"
+    );
+
+    let results: Vec<Vec<f64>> = set
+        .mk_pdfs()
+        .into_iter()
+        .map(|mut pdf| {
+            convolute(
+                grid,
+                &mut pdf,
+                None,
+                orders,
+                bins,
+                lumis,
+                scales,
+                ConvoluteMode::Normal,
+                false,
+            )
+        })
+        .collect();
+
+    let error_type = set
+        .entry("ErrorType")
+        .unwrap_or_else(|| "symmhessian".to_string());
+    let native_cl = set
+        .entry("ErrorConfLevel")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(68.268_949_213_708_58);
+    let rescale = gaussian_quantile(cl) / gaussian_quantile(native_cl);
+
+    let n_bins = results[0].len();
+    let mut central = vec![0.0; n_bins];
+    let mut errminus = vec![0.0; n_bins];
+    let mut errplus = vec![0.0; n_bins];
+
+    for bin in 0..n_bins {
+        let values: Vec<_> = results.iter().map(|member| member[bin]).collect();
+
+        let (c, minus, plus) =
+            combine_members(&values, error_type.as_str(), cl, ReplicaBand::StdDev);
+
+        central[bin] = c;
+        errminus[bin] = minus * rescale;
+        errplus[bin] = plus * rescale;
+    }
+
+    Ok(PdfUncertainty {
+        central,
+        errminus,
+        errplus,
+    })
+}
+
+/// [`PdfUncertainty`] combined in quadrature, per bin, with the αs uncertainty estimated from a
+/// list of companion sets, as returned by [`convolute_uncertainty_with_alphas`].
+pub struct CombinedUncertainty {
+    pub pdf: PdfUncertainty,
+    pub alphas: Vec<f64>,
+    pub errminus: Vec<f64>,
+    pub errplus: Vec<f64>,
+}
+
+/// Like [`convolute_uncertainty`], but additionally folds in the αs(MZ) uncertainty estimated
+/// from `alphas_sets`: each set's central member (carrying its own `alphas_q2`) is convoluted,
+/// and half the max-min spread of the per-bin results across the sets is added in quadrature with
+/// the PDF uncertainty from `set`. If `alphas_sets` is empty the αs component is zero.
+pub fn convolute_uncertainty_with_alphas(
+    grid: &Grid,
+    set: &PdfSet,
+    alphas_sets: &[PdfSet],
+    orders: &[(u32, u32)],
+    bins: &[usize],
+    lumis: &[bool],
+    scales: usize,
+    cl: f64,
+) -> Result<CombinedUncertainty> {
+    let pdf = convolute_uncertainty(grid, set, orders, bins, lumis, scales, cl)?;
+
+    let alphas_results: Vec<Vec<f64>> = alphas_sets
+        .iter()
+        .map(|set| {
+            let mut pdf = set.mk_pdfs().remove(0);
+            convolute(
+                grid,
+                &mut pdf,
+                None,
+                orders,
+                bins,
+                lumis,
+                scales,
+                ConvoluteMode::Normal,
+                false,
+            )
+        })
+        .collect();
+
+    let n_bins = pdf.central.len();
+    let alphas: Vec<_> = (0..n_bins)
+        .map(|bin| {
+            if alphas_results.is_empty() {
+                0.0
+            } else {
+                let values: Vec<_> = alphas_results.iter().map(|member| member[bin]).collect();
+
+                0.5 * (values.iter().copied().fold(f64::MIN, f64::max)
+                    - values.iter().copied().fold(f64::MAX, f64::min))
+            }
+        })
+        .collect();
+
+    let errminus: Vec<_> = pdf
+        .errminus
+        .iter()
+        .zip(alphas.iter())
+        .map(|(errminus, alphas)| -errminus.hypot(*alphas))
+        .collect();
+    let errplus: Vec<_> = pdf
+        .errplus
+        .iter()
+        .zip(alphas.iter())
+        .map(|(errplus, alphas)| errplus.hypot(*alphas))
+        .collect();
+
+    Ok(CombinedUncertainty {
+        pdf,
+        alphas,
+        errminus,
+        errplus,
+    })
+}
+
+/// Convolutes `grid` bin by bin at a dynamic scale instead of the grid's stored central scale.
+/// `dynamic_scale` is called with each bin's limits (one `(lo, hi)` pair per dimension, as
+/// returned by [`Grid::bin_info`]) and must return the squared scale `q2` to use as that bin's
+/// new central scale. The usual `(xir, xif)` factor variation from [`SCALES_VECTOR`] is still
+/// applied on top, by re-deriving `mur2 = xir * xir * q2` and `muf2 = xif * xif * q2` for each of
+/// the first `scales` entries, exactly as [`convolute`] does for the grid's stored scale.
+pub fn convolute_scale_choice(
+    grid: &Grid,
+    lhapdf: &mut Pdf,
+    orders: &[(u32, u32)],
+    lumis: &[bool],
+    scales: usize,
+    mode: ConvoluteMode,
+    dynamic_scale: &dyn Fn(&[(f64, f64)]) -> f64,
+) -> Vec<f64> {
+    let orders: Vec<_> = grid
+        .orders()
+        .iter()
+        .map(|order| {
+            orders.is_empty()
+                || orders
+                    .iter()
+                    .any(|other| (order.alphas == other.0) && (order.alpha == other.1))
+        })
+        .collect();
+
+    // if the field 'Particle' is missing we assume it's a proton PDF
+    let pdf_pdg_id = lhapdf
+        .set()
+        .entry("Particle")
+        .map_or(Ok(2212), |string| string.parse::<i32>())
+        .unwrap();
+
+    let x_max = lhapdf.x_max();
+    let x_min = lhapdf.x_min();
+    let normalizations = grid.bin_info().normalizations();
+
+    let mut results = Vec::new();
+
+    for (bin, limits) in grid.bin_info().limits().into_iter().enumerate() {
+        let q2_central = dynamic_scale(&limits);
+
+        for &(xir, xif) in &SCALES_VECTOR[0..scales] {
+            let mur2 = xir * xir * q2_central;
+            let muf2 = xif * xif * q2_central;
+
+            let mut pdf = |id, x, _| {
+                if x < x_min || x > x_max {
+                    0.0
+                } else {
+                    lhapdf.xfx_q2(id, x, muf2)
+                }
+            };
+            let mut alphas = |_| lhapdf.alphas_q2(mur2);
+            let mut cache = LumiCache::with_one(pdf_pdg_id, &mut pdf, &mut alphas);
+
+            // the grid's own scale variation is bypassed (we've already applied `xir`/`xif` to
+            // the dynamic scale ourselves), so convolute at the trivial (1.0, 1.0) factor
+            let mut result =
+                grid.convolute(&mut cache, &orders, &[bin], lumis, &[(1.0, 1.0)]);
+
+            if let ConvoluteMode::Integrated = mode {
+                result[0] *= normalizations[bin];
+            }
+
+            results.append(&mut result);
+        }
+    }
+
+    results
+}
+
 pub fn convolute_limits(grid: &Grid, bins: &[usize], _: ConvoluteMode) -> Vec<Vec<(f64, f64)>> {
     grid.bin_info()
         .limits()
@@ -204,6 +604,7 @@ pub fn convolute_limits(grid: &Grid, bins: &[usize], _: ConvoluteMode) -> Vec<Ve
 pub fn convolute_subgrid(
     grid: &Grid,
     lhapdf: &mut Pdf,
+    lhapdf2: Option<&mut Pdf>,
     order: usize,
     bin: usize,
     lumi: usize,
@@ -225,9 +626,34 @@ pub fn convolute_subgrid(
         }
     };
     let mut alphas = |q2| lhapdf.alphas_q2(q2);
-    let mut cache = LumiCache::with_one(pdf_pdg_id, &mut pdf, &mut alphas);
 
-    grid.convolute_subgrid(&mut cache, order, bin, lumi, 1.0, 1.0)
+    if let Some(lhapdf2) = lhapdf2 {
+        // proton-nucleus/unequal-beam convolution: x1 is evaluated against `lhapdf`, x2 against
+        // `lhapdf2`
+        let pdf_pdg_id2 = lhapdf2
+            .set()
+            .entry("Particle")
+            .map_or(Ok(2212), |string| string.parse::<i32>())
+            .unwrap();
+
+        let x_max2 = lhapdf2.x_max();
+        let x_min2 = lhapdf2.x_min();
+        let mut pdf2 = |id, x, q2| {
+            if x < x_min2 || x > x_max2 {
+                0.0
+            } else {
+                lhapdf2.xfx_q2(id, x, q2)
+            }
+        };
+        let mut cache =
+            LumiCache::with_two(pdf_pdg_id, &mut pdf, pdf_pdg_id2, &mut pdf2, &mut alphas);
+
+        grid.convolute_subgrid(&mut cache, order, bin, lumi, 1.0, 1.0)
+    } else {
+        let mut cache = LumiCache::with_one(pdf_pdg_id, &mut pdf, &mut alphas);
+
+        grid.convolute_subgrid(&mut cache, order, bin, lumi, 1.0, 1.0)
+    }
 }
 
 pub fn validate_pdfset(argument: &str) -> std::result::Result<(), String> {
@@ -1,13 +1,13 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use flate2::read::GzDecoder;
 use pineappl::grid::{Grid, Order};
 use pineappl::import_only_subgrid::ImportOnlySubgridV1;
 use pineappl::lumi_entry;
 use pineappl::sparse_array3::SparseArray3;
 use pineappl::subgrid::SubgridParams;
+use rayon::prelude::*;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::iter;
+use std::io::{self, BufRead, BufReader, Write};
 use std::path::Path;
 use tar::Archive;
 
@@ -28,8 +28,9 @@ fn read_fktable(reader: impl BufRead, dis_pid: i32) -> Result<Grid> {
     let mut flavor_mask = Vec::<bool>::new();
     let mut x_grid = Vec::new();
     let mut grid = None;
-    let mut arrays = Vec::new();
-    let mut last_bin = 0;
+    // raw `FastKernel` rows, read in full before any parsing so the per-bin groups can be
+    // processed independently and in parallel
+    let mut fastkernel_lines = Vec::new();
 
     let mut hadronic = false;
     let mut ndata: u16 = 0;
@@ -121,10 +122,6 @@ fn read_fktable(reader: impl BufRead, dis_pid: i32) -> Result<Grid> {
                 }
 
                 grid = Some(fktable);
-
-                arrays = iter::repeat(SparseArray3::new(1, nx1, nx2))
-                    .take(flavor_mask.iter().filter(|&&value| value).count())
-                    .collect();
             }
             _ => match section {
                 FkTableSection::GridInfo => {
@@ -162,68 +159,7 @@ fn read_fktable(reader: impl BufRead, dis_pid: i32) -> Result<Grid> {
                     x_grid.push(line.parse()?);
                 }
                 FkTableSection::FastKernel => {
-                    let tokens: Vec<_> = line.split_whitespace().collect();
-
-                    let (bin, x1, x2) = (
-                        tokens[0].parse::<usize>()?,
-                        tokens[1].parse::<usize>()?,
-                        if hadronic {
-                            tokens[2].parse::<usize>()?
-                        } else {
-                            0
-                        },
-                    );
-
-                    // if `bin` has changed, we assume that the subgrids in `array` are finished
-                    if bin > last_bin {
-                        let grid = grid.as_mut().unwrap();
-
-                        for (lumi, array) in arrays.into_iter().enumerate() {
-                            grid.set_subgrid(
-                                0,
-                                last_bin,
-                                lumi,
-                                ImportOnlySubgridV1::new(
-                                    array,
-                                    vec![q0 * q0],
-                                    x_grid.clone(),
-                                    if hadronic { x_grid.clone() } else { vec![1.0] },
-                                )
-                                .into(),
-                            );
-                        }
-
-                        arrays = iter::repeat(SparseArray3::new(1, nx1, nx2))
-                            .take(flavor_mask.iter().filter(|&&value| value).count())
-                            .collect();
-                        last_bin = bin;
-                    }
-
-                    // we can't handle `last_bin > bin`
-                    assert_eq!(last_bin, bin);
-
-                    let grid_values: Vec<f64> = tokens
-                        .iter()
-                        .skip(if hadronic { 3 } else { 2 })
-                        .zip(flavor_mask.iter())
-                        .filter(|&(_, &mask)| mask)
-                        .map(|(string, _)| {
-                            string.parse().with_context(|| {
-                                format!("failed to parse floating point number from '{string}'")
-                            })
-                        })
-                        .collect::<Result<_>>()?;
-
-                    assert_eq!(grid_values.len(), arrays.len());
-
-                    for (array, value) in arrays
-                        .iter_mut()
-                        .zip(grid_values.iter())
-                        .filter(|(_, value)| **value != 0.0)
-                    {
-                        array[[0, x1, x2]] =
-                            x_grid[x1] * if hadronic { x_grid[x2] } else { 1.0 } * value;
-                    }
+                    fastkernel_lines.push(line);
                 }
                 _ => {}
             },
@@ -234,19 +170,92 @@ fn read_fktable(reader: impl BufRead, dis_pid: i32) -> Result<Grid> {
 
     let mut grid = grid.unwrap();
 
-    for (lumi, array) in arrays.into_iter().enumerate() {
-        grid.set_subgrid(
-            0,
-            last_bin,
-            lumi,
-            ImportOnlySubgridV1::new(
-                array,
-                vec![q0 * q0],
-                x_grid.clone(),
-                if hadronic { x_grid.clone() } else { vec![1.0] },
-            )
-            .into(),
-        );
+    // column index (within the per-row flavor combinations) of each active lumi, in the order
+    // `flavor_mask` enumerates them
+    let active_columns: Vec<_> = flavor_mask
+        .iter()
+        .enumerate()
+        .filter(|&(_, &value)| value)
+        .map(|(index, _)| index)
+        .collect();
+    let value_offset = if hadronic { 3 } else { 2 };
+
+    // group the rows by bin, preserving the order in which the bins appear in the file; we read
+    // the whole section before parsing it so that the (expensive) population of the per-lumi
+    // `SparseArray3` arrays can be parallelized across the lumis of a single bin, processing one
+    // bin group at a time to bound peak memory use
+    let mut bins: Vec<(usize, Vec<&str>)> = Vec::new();
+
+    for line in &fastkernel_lines {
+        let bin = line
+            .split_whitespace()
+            .next()
+            .unwrap()
+            .parse::<usize>()
+            .with_context(|| format!("failed to parse bin index from '{line}'"))?;
+
+        if bins.last().map_or(true, |&(last_bin, _)| last_bin != bin) {
+            ensure!(
+                bins.last().map_or(true, |&(last_bin, _)| bin > last_bin),
+                "bin index {bin} out of order: expected it to be greater than the previous bin"
+            );
+
+            bins.push((bin, Vec::new()));
+        }
+
+        bins.last_mut().unwrap().1.push(line);
+    }
+
+    let n_bins = bins.len();
+
+    for (progress, (bin, rows)) in bins.into_iter().enumerate() {
+        // overwrite the same line instead of printing one per bin, which would otherwise flood
+        // stderr with thousands of lines for multi-hundred-MB NNPDF-style tables
+        eprint!("\rreading bin {}/{n_bins}", progress + 1);
+        io::stderr().flush().ok();
+
+        let arrays: Vec<SparseArray3<f64>> = active_columns
+            .par_iter()
+            .map(|&column| -> Result<_> {
+                let mut array = SparseArray3::new(1, nx1, nx2);
+
+                for row in &rows {
+                    let tokens: Vec<_> = row.split_whitespace().collect();
+                    let x1 = tokens[1].parse::<usize>()?;
+                    let x2 = if hadronic { tokens[2].parse::<usize>()? } else { 0 };
+                    let string = tokens[value_offset + column];
+                    let value: f64 = string.parse().with_context(|| {
+                        format!("failed to parse floating point number from '{string}'")
+                    })?;
+
+                    if value != 0.0 {
+                        array[[0, x1, x2]] =
+                            x_grid[x1] * if hadronic { x_grid[x2] } else { 1.0 } * value;
+                    }
+                }
+
+                Ok(array)
+            })
+            .collect::<Result<_>>()?;
+
+        for (lumi, array) in arrays.into_iter().enumerate() {
+            grid.set_subgrid(
+                0,
+                bin,
+                lumi,
+                ImportOnlySubgridV1::new(
+                    array,
+                    vec![q0 * q0],
+                    x_grid.clone(),
+                    if hadronic { x_grid.clone() } else { vec![1.0] },
+                )
+                .into(),
+            );
+        }
+    }
+
+    if n_bins > 0 {
+        eprintln!();
     }
 
     Ok(grid)
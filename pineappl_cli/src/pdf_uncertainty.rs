@@ -6,14 +6,73 @@ use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
 
-use super::helpers::create_table;
+use super::helpers::{self, create_table, ReplicaBand};
+
+/// Error-treatment convention used to turn a set of convoluted member predictions into a central
+/// value and an uncertainty band.
+#[derive(Clone, Copy)]
+pub enum ErrorType {
+    /// Infer the convention from the PDF set's own `ErrorType` metadata.
+    Auto,
+    /// Symmetric-Hessian eigenvector format: member 0 is the central value, members `1..=n` are
+    /// symmetric eigenvector displacements.
+    SymmHessian,
+    /// Asymmetric-Hessian eigenvector format: member 0 is the central value, members come in
+    /// `(+, -)` pairs `(2k-1, 2k)`.
+    Hessian,
+    /// Monte-Carlo replica format: the uncertainty is derived from the sample of members.
+    Replicas,
+}
+
+/// How the uncertainty band of a replica set should be summarized.
+#[derive(Clone, Copy)]
+pub enum Band {
+    /// Report the sample standard deviation of the replicas.
+    StdDev,
+    /// Report the symmetric `cl`% percentile band of the replicas.
+    Percentile,
+}
+
+struct Uncertainty {
+    central: f64,
+    errminus: f64,
+    errplus: f64,
+}
+
+fn error_type_of(set: &PdfSet, error_type: ErrorType) -> String {
+    match error_type {
+        ErrorType::Auto => set
+            .entry("ErrorType")
+            .unwrap_or_else(|| "symmhessian".to_string()),
+        ErrorType::SymmHessian => "symmhessian".to_string(),
+        ErrorType::Hessian => "hessian".to_string(),
+        ErrorType::Replicas => "replicas".to_string(),
+    }
+}
+
+fn uncertainty(values: &[f64], error_type: &str, cl: f64, band: Band) -> Uncertainty {
+    let band = match band {
+        Band::StdDev => ReplicaBand::StdDev,
+        Band::Percentile => ReplicaBand::Percentile,
+    };
+    let (central, errminus, errplus) = helpers::combine_members(values, error_type, cl, band);
+
+    Uncertainty {
+        central,
+        errminus,
+        errplus,
+    }
+}
 
 pub fn subcommand(
     input: &str,
     pdfset: &str,
+    alphas_pdfsets: &[String],
     cl: f64,
     threads: usize,
     orders: &[(u32, u32)],
+    error_type: ErrorType,
+    band: Band,
 ) -> Result<Table, Box<dyn Error>> {
     let grid = Grid::read(BufReader::new(File::open(input)?))?;
     let set = PdfSet::new(&pdfset.parse().map_or_else(
@@ -21,6 +80,7 @@ pub fn subcommand(
         |lhaid| lhapdf::lookup_pdf(lhaid).unwrap().0,
     ));
     let pdfs = set.mk_pdfs();
+    let error_type = error_type_of(&set, error_type);
 
     let orders: Vec<_> = grid
         .orders()
@@ -38,20 +98,33 @@ pub fn subcommand(
         .build_global()
         .unwrap();
 
-    let results: Vec<f64> = pdfs
-        .into_par_iter()
-        .flat_map(|pdf| {
-            grid.convolute(
-                &|id, x, q2| pdf.xfx_q2(id, x, q2),
-                &|id, x, q2| pdf.xfx_q2(id, x, q2),
-                &|q2| pdf.alphas_q2(q2),
-                &orders,
-                &[],
-                &[],
-                &[(1.0, 1.0)],
-            )
+    let convolute_central = |pdf: &lhapdf::Pdf| {
+        grid.convolute(
+            &|id, x, q2| pdf.xfx_q2(id, x, q2),
+            &|id, x, q2| pdf.xfx_q2(id, x, q2),
+            &|q2| pdf.alphas_q2(q2),
+            &orders,
+            &[],
+            &[],
+            &[(1.0, 1.0)],
+        )
+    };
+
+    let results: Vec<f64> = pdfs.into_par_iter().flat_map(|pdf| convolute_central(&pdf)).collect();
+
+    // fold in the alphas uncertainty of the companion sets (each carrying its own `alphas_q2`) in
+    // quadrature with the PDF uncertainty computed from `pdfset`'s members
+    let alphas_results: Vec<Vec<f64>> = alphas_pdfsets
+        .iter()
+        .map(|pdfset| -> Result<_, Box<dyn Error>> {
+            let set = PdfSet::new(&pdfset.parse().map_or_else(
+                |_| pdfset.to_string(),
+                |lhaid| lhapdf::lookup_pdf(lhaid).unwrap().0,
+            ));
+            let pdf = set.mk_pdfs().remove(0);
+            Ok(convolute_central(&pdf))
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     let bin_info = grid.bin_info();
     let left_limits: Vec<_> = (0..bin_info.dimensions())
@@ -73,6 +146,9 @@ pub fn subcommand(
     title.add_cell(cell!(c->"integ"));
     title.add_cell(cell!(c->"neg unc"));
     title.add_cell(cell!(c->"pos unc"));
+    if !alphas_pdfsets.is_empty() {
+        title.add_cell(cell!(c->"alphas unc"));
+    }
 
     let mut table = create_table();
     table.set_titles(title);
@@ -82,9 +158,20 @@ pub fn subcommand(
             .iter()
             .skip(bin)
             .step_by(bin_info.bins())
-            .cloned()
+            .copied()
             .collect();
-        let uncertainty = set.uncertainty(&values, cl, false);
+        let pdf_uncertainty = uncertainty(&values, &error_type, cl, band);
+
+        // half the max-min spread across the companion sets' central predictions
+        let alphas_unc = if alphas_pdfsets.is_empty() {
+            0.0
+        } else {
+            let central_values: Vec<_> = alphas_results.iter().map(|r| r[bin]).collect();
+            0.5 * (central_values.iter().cloned().fold(f64::MIN, f64::max)
+                - central_values.iter().cloned().fold(f64::MAX, f64::min))
+        };
+        let combined_errplus = pdf_uncertainty.errplus.hypot(alphas_unc);
+        let combined_errminus = -pdf_uncertainty.errminus.hypot(alphas_unc);
 
         let row = table.add_empty_row();
 
@@ -93,14 +180,21 @@ pub fn subcommand(
             row.add_cell(cell!(r->&format!("{}", left[bin])));
             row.add_cell(cell!(r->&format!("{}", right[bin])));
         }
-        row.add_cell(cell!(r->&format!("{:.7e}", uncertainty.central)));
-        row.add_cell(cell!(r->&format!("{:.7e}", uncertainty.central * normalizations[bin])));
+        row.add_cell(cell!(r->&format!("{:.7e}", pdf_uncertainty.central)));
         row.add_cell(
-            cell!(r->&format!("{:.2}%", (-uncertainty.errminus / uncertainty.central) * 100.0)),
+            cell!(r->&format!("{:.7e}", pdf_uncertainty.central * normalizations[bin])),
         );
         row.add_cell(
-            cell!(r->&format!("{:.2}%", (uncertainty.errplus / uncertainty.central) * 100.0)),
+            cell!(r->&format!("{:.2}%", (combined_errminus / pdf_uncertainty.central) * 100.0)),
         );
+        row.add_cell(
+            cell!(r->&format!("{:.2}%", (combined_errplus / pdf_uncertainty.central) * 100.0)),
+        );
+        if !alphas_pdfsets.is_empty() {
+            row.add_cell(
+                cell!(r->&format!("{:.2}%", (alphas_unc / pdf_uncertainty.central) * 100.0)),
+            );
+        }
     }
 
     Ok(table)
@@ -1,6 +1,6 @@
 use super::helpers;
 use super::{GlobalConfiguration, Subcommand};
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::builder::{PossibleValuesParser, TypedValueParser};
 use clap::{
     value_parser, Arg, ArgAction, ArgMatches, Args, Command, Error, FromArgMatches, Parser,
@@ -10,9 +10,10 @@ use pineappl::bin::BinRemapper;
 use pineappl::fk_table::{FkAssumptions, FkTable};
 use pineappl::lumi::LumiEntry;
 use pineappl::pids;
+use serde::Deserialize;
 use std::fs;
-use std::ops::{Deref, RangeInclusive};
-use std::path::PathBuf;
+use std::ops::{Deref, Range, RangeInclusive};
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 /// Write a grid modified by various operations.
@@ -24,14 +25,176 @@ pub struct Opts {
     /// Path of the modified PineAPPL file.
     #[arg(value_hint = ValueHint::FilePath)]
     output: PathBuf,
+    /// Read an ordered list of operations from a YAML or TOML file.
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    ops_file: Option<PathBuf>,
     #[command(flatten)]
     more_args: MoreArgs,
 }
 
+/// A single operation as it appears in an `--ops-file` recipe. Variant names and payloads mirror
+/// [`OpsArg`], using plain data types that `serde` can deserialize directly. `OptimizeFkTable`,
+/// `RemapNorm` and `RemapNormIgnore` have no recipe counterpart.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RecipeOp {
+    Cc1(bool),
+    Cc2(bool),
+    CollapseDim(usize),
+    DedupChannels(i64),
+    DeleteBins(Vec<(usize, usize)>),
+    DeleteKey(String),
+    MergeBins(Vec<(usize, usize)>),
+    Optimize(bool),
+    Remap(String),
+    RewriteChannel((usize, String)),
+    Scale(f64),
+    ScaleByBin(Vec<f64>),
+    ScaleByOrder(Vec<f64>),
+    ScaleBySlice((usize, Vec<f64>)),
+    SetKeyFile((String, String)),
+    SetKeyValue((String, String)),
+    SplitLumi(bool),
+    Upgrade(bool),
+}
+
+impl From<RecipeOp> for OpsArg {
+    fn from(op: RecipeOp) -> Self {
+        match op {
+            RecipeOp::Cc1(enable) => Self::Cc1(enable),
+            RecipeOp::Cc2(enable) => Self::Cc2(enable),
+            RecipeOp::CollapseDim(dim) => Self::CollapseDim(dim),
+            RecipeOp::DedupChannels(ulps) => Self::DedupChannels(ulps),
+            RecipeOp::DeleteBins(ranges) => {
+                Self::DeleteBins(ranges.into_iter().map(|(a, b)| a..=b).collect())
+            }
+            RecipeOp::DeleteKey(key) => Self::DeleteKey(key),
+            RecipeOp::MergeBins(ranges) => {
+                Self::MergeBins(ranges.into_iter().map(|(a, b)| a..=b).collect())
+            }
+            RecipeOp::Optimize(enable) => Self::Optimize(enable),
+            RecipeOp::Remap(remapping) => Self::Remap(remapping),
+            RecipeOp::RewriteChannel((index, channel)) => {
+                Self::RewriteChannel((index, channel.parse().unwrap()))
+            }
+            RecipeOp::Scale(factor) => Self::Scale(factor),
+            RecipeOp::ScaleByBin(factors) => Self::ScaleByBin(factors),
+            RecipeOp::ScaleByOrder(factors) => Self::ScaleByOrder(factors),
+            RecipeOp::ScaleBySlice((dim, factors)) => Self::ScaleBySlice((dim, factors)),
+            RecipeOp::SetKeyFile((key, path)) => Self::SetKeyFile(vec![key, path]),
+            RecipeOp::SetKeyValue((key, value)) => Self::SetKeyValue(vec![key, value]),
+            RecipeOp::SplitLumi(enable) => Self::SplitLumi(enable),
+            RecipeOp::Upgrade(enable) => Self::Upgrade(enable),
+        }
+    }
+}
+
+/// Whether the operations from an `--ops-file` recipe are applied before or after the ones given
+/// on the command line.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RecipeOrder {
+    First,
+    Last,
+}
+
+impl Default for RecipeOrder {
+    fn default() -> Self {
+        Self::First
+    }
+}
+
+#[derive(Deserialize)]
+struct Recipe {
+    #[serde(default)]
+    order: RecipeOrder,
+    ops: Vec<RecipeOp>,
+}
+
+fn read_recipe(path: &Path) -> Result<Recipe> {
+    let contents =
+        fs::read_to_string(path).context(format!("unable to read '{}'", path.display()))?;
+
+    if path.extension().map_or(false, |ext| ext == "toml") {
+        toml::from_str(&contents).context(format!("unable to parse '{}'", path.display()))
+    } else {
+        serde_yaml::from_str(&contents).context(format!("unable to parse '{}'", path.display()))
+    }
+}
+
+// groups consecutive bins whose limits agree in every dimension except `dim`
+fn collapse_dim_groups(
+    limits: &[Vec<(f64, f64)>],
+    dimensions: usize,
+    dim: usize,
+) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    let mut start = 0;
+
+    for bin in 1..=limits.len() {
+        let same = (bin < limits.len())
+            && (0..dimensions)
+                .filter(|&d| d != dim)
+                .all(|d| limits[bin][d] == limits[start][d]);
+
+        if !same {
+            groups.push(start..bin);
+            start = bin;
+        }
+    }
+
+    groups
+}
+
+// checks that no two (necessarily non-adjacent, since `collapse_dim_groups` already merges
+// adjacent runs) groups share the same remaining-dimension coordinates, which would mean `dim`
+// isn't the fastest-varying dimension and the groups can't be expressed as a single `merge_bins`
+// range each
+fn collapse_dim_groups_are_contiguous(
+    limits: &[Vec<(f64, f64)>],
+    dimensions: usize,
+    dim: usize,
+    groups: &[Range<usize>],
+) -> bool {
+    groups.iter().enumerate().all(|(i, group)| {
+        !groups[(i + 1)..].iter().any(|other| {
+            (0..dimensions)
+                .filter(|&d| d != dim)
+                .all(|d| limits[other.start][d] == limits[group.start][d])
+        })
+    })
+}
+
+// maps `--scale-by-slice`'s per-slice `factors` onto a per-bin scale factor vector, matching each
+// bin's `dim`-coordinate against the distinct coordinates found across `bin_coordinates`
+fn scale_by_slice_factors(
+    bin_coordinates: &[(f64, f64)],
+    factors: &[f64],
+    dim: usize,
+) -> Result<Vec<f64>> {
+    let mut distinct = bin_coordinates.to_vec();
+    distinct.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    distinct.dedup();
+
+    if distinct.len() != factors.len() {
+        bail!(
+            "expected {} factors for dimension {dim}, found {}",
+            distinct.len(),
+            factors.len()
+        );
+    }
+
+    Ok(bin_coordinates
+        .iter()
+        .map(|coordinate| factors[distinct.iter().position(|other| other == coordinate).unwrap()])
+        .collect())
+}
+
 #[derive(Clone)]
 enum OpsArg {
     Cc1(bool),
     Cc2(bool),
+    CollapseDim(usize),
     DedupChannels(i64),
     DeleteBins(Vec<RangeInclusive<usize>>),
     DeleteKey(String),
@@ -45,6 +208,7 @@ enum OpsArg {
     Scale(f64),
     ScaleByBin(Vec<f64>),
     ScaleByOrder(Vec<f64>),
+    ScaleBySlice((usize, Vec<f64>)),
     SetKeyFile(Vec<String>),
     SetKeyValue(Vec<String>),
     SplitLumi(bool),
@@ -103,6 +267,21 @@ impl FromArgMatches for MoreArgs {
                         });
                     }
                 }
+                "collapse_dim" => {
+                    let arguments: Vec<Vec<_>> = matches
+                        .remove_occurrences(&id)
+                        .unwrap()
+                        .map(Iterator::collect)
+                        .collect();
+
+                    for (index, arg) in indices.into_iter().zip(arguments.into_iter()) {
+                        assert_eq!(arg.len(), 1);
+                        args[index] = Some(match id.as_str() {
+                            "collapse_dim" => OpsArg::CollapseDim(arg[0]),
+                            _ => unreachable!(),
+                        });
+                    }
+                }
                 "dedup_channels" => {
                     let arguments: Vec<Vec<_>> = matches
                         .remove_occurrences(&id)
@@ -196,6 +375,25 @@ impl FromArgMatches for MoreArgs {
                         )));
                     }
                 }
+                "scale_by_slice" => {
+                    let arguments: Vec<Vec<String>> = matches
+                        .remove_occurrences(&id)
+                        .unwrap()
+                        .map(Iterator::collect)
+                        .collect();
+
+                    for (index, arg) in indices.into_iter().zip(arguments.into_iter()) {
+                        assert_eq!(arg.len(), 2);
+
+                        args[index] = Some(OpsArg::ScaleBySlice((
+                            str::parse(&arg[0]).unwrap(),
+                            arg[1]
+                                .split(',')
+                                .map(|factor| str::parse(factor).unwrap())
+                                .collect(),
+                        )));
+                    }
+                }
                 "scale_by_bin" | "scale_by_order" => {
                     let arguments: Vec<Vec<_>> = matches
                         .remove_occurrences(&id)
@@ -268,6 +466,15 @@ impl Args for MoreArgs {
                 .value_name("ENABLE")
                 .value_parser(clap::value_parser!(bool)),
         )
+        .arg(
+            Arg::new("collapse_dim")
+                .action(ArgAction::Append)
+                .help("Integrate out the given dimension of the bin remapper, summing bins that agree in every other dimension")
+                .long("collapse-dim")
+                .num_args(1)
+                .value_name("DIM")
+                .value_parser(value_parser!(usize)),
+        )
         .arg(
             Arg::new("dedup_channels")
                 .action(ArgAction::Append)
@@ -395,6 +602,14 @@ impl Args for MoreArgs {
                 .value_name("AS,AL,LR,LF")
                 .value_parser(value_parser!(f64)),
         )
+        .arg(
+            Arg::new("scale_by_slice")
+                .action(ArgAction::Append)
+                .help("Scale each bin by a factor looked up by its coordinate along dimension DIM")
+                .long("scale-by-slice")
+                .num_args(2)
+                .value_names(["DIM", "FACTORS"]),
+        )
         .arg(
             Arg::new("set_key_value")
                 .action(ArgAction::Append)
@@ -446,7 +661,19 @@ impl Subcommand for Opts {
     fn run(&self, _: &GlobalConfiguration) -> Result<ExitCode> {
         let mut grid = helpers::read_grid(&self.input)?;
 
-        for arg in &self.more_args.args {
+        let mut ops = self.more_args.args.clone();
+
+        if let Some(path) = &self.ops_file {
+            let recipe = read_recipe(path)?;
+            let file_ops: Vec<_> = recipe.ops.into_iter().map(OpsArg::from).collect();
+
+            match recipe.order {
+                RecipeOrder::First => ops.splice(0..0, file_ops),
+                RecipeOrder::Last => ops.extend(file_ops),
+            };
+        }
+
+        for arg in &ops {
             match arg {
                 OpsArg::Cc1(true) | OpsArg::Cc2(true) => {
                     let cc1 = matches!(arg, OpsArg::Cc1(true));
@@ -495,6 +722,82 @@ impl Subcommand for Opts {
                     grid.set_key_value("initial_state_2", &initial_state_2.to_string());
                     grid.set_lumis(lumis);
                 }
+                OpsArg::CollapseDim(dim) => {
+                    let remapper = grid
+                        .remapper()
+                        .ok_or_else(|| anyhow!("grid does not have a remapper"))?;
+                    let dimensions = remapper.dimensions();
+
+                    if *dim >= dimensions {
+                        bail!(
+                            "dimension {dim} is out of range for a grid with {dimensions} dimensions"
+                        );
+                    }
+
+                    let limits: Vec<_> = remapper
+                        .limits()
+                        .chunks_exact(dimensions)
+                        .map(<[_]>::to_vec)
+                        .collect();
+
+                    let groups = collapse_dim_groups(&limits, dimensions, *dim);
+
+                    // `merge_bins` only accepts a contiguous range, so collapsing `dim` is only
+                    // correct if it's the fastest-varying dimension in the bin layout, i.e. every
+                    // remaining-dimension coordinate forms a single contiguous run of bins;
+                    // reject the same coordinates reappearing in a later, non-adjacent group
+                    // instead of silently merging the wrong bins
+                    if !collapse_dim_groups_are_contiguous(&limits, dimensions, *dim, &groups) {
+                        bail!(
+                            "dimension {dim} is not the fastest-varying dimension of the bin \
+                             layout, so bins with equal coordinates in the remaining dimensions \
+                             are not contiguous and cannot be collapsed"
+                        );
+                    }
+
+                    let new_normalizations: Vec<_> = groups
+                        .iter()
+                        .map(|group| {
+                            (0..dimensions)
+                                .filter(|&d| d != *dim)
+                                .map(|d| limits[group.start][d].1 - limits[group.start][d].0)
+                                .product()
+                        })
+                        .collect();
+                    let new_limits: Vec<_> = groups
+                        .iter()
+                        .flat_map(|group| {
+                            (0..dimensions)
+                                .filter(|&d| d != *dim)
+                                .map(|d| limits[group.start][d])
+                        })
+                        .collect();
+
+                    // merge in reverse order, so that merging one group doesn't shift the bin
+                    // indices of the groups that still need to be merged
+                    for group in groups.iter().rev() {
+                        if group.len() > 1 {
+                            grid.merge_bins(group.clone())?;
+                        }
+                    }
+
+                    grid.set_remapper(BinRemapper::new(new_normalizations, new_limits).unwrap())?;
+
+                    // the collapsed dimension no longer exists, and the labels of the dimensions
+                    // after it shift down by one
+                    let key_values = grid.key_values_mut();
+                    key_values.remove(&format!("x{}_label", dim + 1));
+                    key_values.remove(&format!("x{}_label_tex", dim + 1));
+                    key_values.remove(&format!("x{}_unit", dim + 1));
+                    for d in (*dim + 1)..dimensions {
+                        for suffix in ["_label", "_label_tex", "_unit"] {
+                            if let Some(value) = key_values.remove(&format!("x{}{suffix}", d + 1))
+                            {
+                                key_values.insert(format!("x{d}{suffix}"), value);
+                            }
+                        }
+                    }
+                }
                 OpsArg::DedupChannels(ulps) => {
                     grid.dedup_channels(*ulps);
                 }
@@ -564,6 +867,28 @@ impl Subcommand for Opts {
                 OpsArg::ScaleByOrder(factors) => {
                     grid.scale_by_order(factors[0], factors[1], factors[2], factors[3], 1.0);
                 }
+                OpsArg::ScaleBySlice((dim, factors)) => {
+                    let remapper = grid
+                        .remapper()
+                        .ok_or_else(|| anyhow!("grid does not have a remapper"))?;
+                    let dimensions = remapper.dimensions();
+
+                    if *dim >= dimensions {
+                        bail!(
+                            "dimension {dim} is out of range for a grid with {dimensions} dimensions"
+                        );
+                    }
+
+                    let bin_coordinates: Vec<_> = remapper
+                        .limits()
+                        .chunks_exact(dimensions)
+                        .map(|chunk| chunk[*dim])
+                        .collect();
+
+                    let bin_factors = scale_by_slice_factors(&bin_coordinates, factors, *dim)?;
+
+                    grid.scale_by_bin(&bin_factors);
+                }
                 OpsArg::SetKeyValue(key_value) => {
                     grid.set_key_value(&key_value[0], &key_value[1]);
                 }
@@ -583,3 +908,57 @@ impl Subcommand for Opts {
         helpers::write_grid(&self.output, &grid)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 2x2 (pT, y) grid laid out with `y` as the fastest-varying dimension: collapsing `y`
+    // (dim 1) groups adjacent bins, collapsing `pT` (dim 0) does not
+    fn pt_y_limits() -> Vec<Vec<(f64, f64)>> {
+        vec![
+            vec![(0.0, 1.0), (0.0, 1.0)],
+            vec![(0.0, 1.0), (1.0, 2.0)],
+            vec![(1.0, 2.0), (0.0, 1.0)],
+            vec![(1.0, 2.0), (1.0, 2.0)],
+        ]
+    }
+
+    #[test]
+    fn collapse_dim_groups_fastest_varying_dimension_is_contiguous() {
+        let limits = pt_y_limits();
+        let groups = collapse_dim_groups(&limits, 2, 1);
+
+        assert_eq!(groups, vec![0..2, 2..4]);
+        assert!(collapse_dim_groups_are_contiguous(&limits, 2, 1, &groups));
+    }
+
+    #[test]
+    fn collapse_dim_groups_non_fastest_varying_dimension_is_rejected() {
+        let limits = pt_y_limits();
+        let groups = collapse_dim_groups(&limits, 2, 0);
+
+        // every bin ends up in its own group, since `pT` isn't the fastest-varying dimension
+        assert_eq!(groups, vec![0..1, 1..2, 2..3, 3..4]);
+        assert!(!collapse_dim_groups_are_contiguous(&limits, 2, 0, &groups));
+    }
+
+    #[test]
+    fn scale_by_slice_factors_matches_coordinates_to_factors() {
+        let bin_coordinates = vec![(0.0, 1.0), (0.0, 1.0), (1.0, 2.0), (1.0, 2.0)];
+        let factors = scale_by_slice_factors(&bin_coordinates, &[2.0, 3.0], 0).unwrap();
+
+        assert_eq!(factors, vec![2.0, 2.0, 3.0, 3.0]);
+    }
+
+    #[test]
+    fn scale_by_slice_factors_rejects_factor_count_mismatch() {
+        let bin_coordinates = vec![(0.0, 1.0), (0.0, 1.0), (1.0, 2.0), (1.0, 2.0)];
+        let result = scale_by_slice_factors(&bin_coordinates, &[2.0, 3.0, 4.0], 0);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "expected 2 factors for dimension 0, found 3"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use js_sys::Function;
+use pineappl::grid::Grid;
+use pineappl::lumi::LumiCache;
+use wasm_bindgen::prelude::*;
+
+/// Opaque, `wasm-bindgen`-friendly handle around a deserialized [`Grid`]. Constructing this is the
+/// expensive part (it parses the whole grid), so it is kept separate from [`WasmGrid::convolute`],
+/// which can then be called many times with different PDFs.
+#[wasm_bindgen]
+pub struct WasmGrid {
+    grid: Grid,
+}
+
+fn call_f64(function: &Function, args: &[f64]) -> f64 {
+    let this = JsValue::NULL;
+    let args: js_sys::Array = args.iter().map(|&arg| JsValue::from_f64(arg)).collect();
+
+    function
+        .apply(&this, &args)
+        .unwrap_or(JsValue::NULL)
+        .as_f64()
+        .unwrap_or(0.0)
+}
+
+#[wasm_bindgen]
+impl WasmGrid {
+    /// Reads a grid from the uncompressed `.pineappl` bytes `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `JsError` if `buffer` does not contain a valid grid.
+    #[wasm_bindgen(constructor)]
+    pub fn new(buffer: &[u8]) -> Result<Self, JsError> {
+        Ok(Self {
+            grid: Grid::read(buffer).map_err(|err| JsError::new(&err.to_string()))?,
+        })
+    }
+
+    /// Convolutes the grid with a PDF given as the JS callbacks `xfx_q2` and `alphas_q2`, and
+    /// returns the per-bin differential cross sections as a `Float64Array`. `xfx_q2` is called as
+    /// `xfx_q2(pid, x, q2)` and must return `x * f(x, q2)`, and `alphas_q2` is called as
+    /// `alphas_q2(q2)`.
+    #[wasm_bindgen]
+    pub fn convolute(&self, pdg_id: i32, xfx_q2: &Function, alphas_q2: &Function) -> Vec<f64> {
+        let mut pdf = |id, x, q2| call_f64(xfx_q2, &[f64::from(id), x, q2]);
+        let mut alphas = |q2| call_f64(alphas_q2, &[q2]);
+        let mut cache = LumiCache::with_one(pdg_id, &mut pdf, &mut alphas);
+
+        self.grid
+            .convolute(&mut cache, &[], &[], &[], &[(1.0, 1.0)])
+    }
+
+    /// Convolutes the grid like [`Self::convolute`], but returns the integrated (bin-width
+    /// weighted) cross sections instead of the differential ones.
+    #[wasm_bindgen(js_name = "convoluteIntegrated")]
+    pub fn convolute_integrated(
+        &self,
+        pdg_id: i32,
+        xfx_q2: &Function,
+        alphas_q2: &Function,
+    ) -> Vec<f64> {
+        let diff = self.convolute(pdg_id, xfx_q2, alphas_q2);
+        let normalizations = self.grid.bin_info().normalizations();
+
+        diff.into_iter()
+            .zip(normalizations.iter())
+            .map(|(value, norm)| value * norm)
+            .collect()
+    }
+
+    /// Returns the number of bins of this grid.
+    #[wasm_bindgen(js_name = "bins")]
+    pub fn bins(&self) -> usize {
+        self.grid.bin_info().bins()
+    }
+}
@@ -0,0 +1,6 @@
+//! `wasm-bindgen` bindings for `pineappl`, allowing grids to be convoluted with a PDF from
+//! JavaScript without linking LHAPDF.
+
+mod grid;
+
+pub use grid::WasmGrid;